@@ -0,0 +1,457 @@
+use core::alloc::AllocError;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::arch::aarch64::kernel::{get_boot_info_address, get_limit, get_ram_address};
+use crate::arch::aarch64::mm::paging::{BasePageSize, PageSize};
+use crate::arch::aarch64::mm::{PhysAddr, VirtAddr};
+use crate::environment::is_uhyve;
+use crate::mm;
+use crate::synch::spinlock::SpinlockIrqSave;
+
+/// Maximum amount of physical memory this backend can track, one bit per
+/// `BasePageSize`. At 4 KiB pages this covers 128 GiB of RAM.
+const MAX_PHYSICAL_MEMORY: usize = 128 * 1024 * 1024 * 1024;
+const WORDS: usize = MAX_PHYSICAL_MEMORY / BasePageSize::SIZE / u32::BITS as usize;
+
+struct Bitmap {
+	/// Base address the bitmap's bit 0 corresponds to.
+	base: usize,
+	/// Number of frames actually backed by RAM (the rest stay permanently set).
+	frame_count: usize,
+	words: [u32; WORDS],
+}
+
+impl Bitmap {
+	const fn new() -> Self {
+		Self {
+			base: 0,
+			frame_count: 0,
+			words: [u32::MAX; WORDS],
+		}
+	}
+
+	fn init(&mut self, base: usize, size: usize) {
+		assert!(
+			size <= MAX_PHYSICAL_MEMORY,
+			"Detected {:#X} bytes of RAM, which exceeds the {:#X} bytes this bitmap can track",
+			size,
+			MAX_PHYSICAL_MEMORY
+		);
+
+		self.base = base;
+		self.frame_count = size / BasePageSize::SIZE;
+
+		for word in self.words.iter_mut() {
+			*word = u32::MAX;
+		}
+
+		for frame in 0..self.frame_count {
+			self.words[frame / u32::BITS as usize] &= !(1 << (frame % u32::BITS as usize));
+		}
+	}
+
+	fn frame_of(&self, address: usize) -> usize {
+		(address - self.base) / BasePageSize::SIZE
+	}
+
+	/// Like `frame_of`, but for addresses coming from outside the allocator
+	/// (e.g. a `reserve`/`deallocate` caller), which are not guaranteed to
+	/// fall inside the tracked RAM span. Returns `None` instead of
+	/// underflowing `address - self.base` or yielding a frame index that
+	/// would run past `self.frame_count`.
+	fn frame_range(&self, address: usize, frame_count: usize) -> Option<usize> {
+		let first_frame = address.checked_sub(self.base)? / BasePageSize::SIZE;
+		(first_frame + frame_count <= self.frame_count).then_some(first_frame)
+	}
+
+	fn is_clear(&self, frame: usize) -> bool {
+		self.words[frame / u32::BITS as usize] & (1 << (frame % u32::BITS as usize)) == 0
+	}
+
+	fn set(&mut self, frame: usize) {
+		self.words[frame / u32::BITS as usize] |= 1 << (frame % u32::BITS as usize);
+	}
+
+	fn clear(&mut self, frame: usize) {
+		self.words[frame / u32::BITS as usize] &= !(1 << (frame % u32::BITS as usize));
+	}
+
+	/// Finds and sets the first run of `count` consecutive clear frames
+	/// starting at a multiple of `align_frames`, returning its first frame.
+	fn find_and_set(&mut self, count: usize, align_frames: usize) -> Option<usize> {
+		if count == 1 && align_frames == 1 {
+			return self.find_and_set_single();
+		}
+
+		let mut frame = 0;
+		while frame + count <= self.frame_count {
+			if frame % align_frames != 0 {
+				frame += 1;
+				continue;
+			}
+
+			if (frame..frame + count).all(|f| self.is_clear(f)) {
+				for f in frame..frame + count {
+					self.set(f);
+				}
+				return Some(frame);
+			}
+
+			frame += 1;
+		}
+
+		None
+	}
+
+	/// Fast path for the common single-frame, page-aligned allocation: scan
+	/// word by word, skipping words that are already full.
+	fn find_and_set_single(&mut self) -> Option<usize> {
+		for (word_index, word) in self.words.iter_mut().enumerate() {
+			if *word == u32::MAX {
+				continue;
+			}
+
+			let bit = word.trailing_ones();
+			let frame = word_index * u32::BITS as usize + bit as usize;
+			if frame >= self.frame_count {
+				return None;
+			}
+
+			*word |= 1 << bit;
+			return Some(frame);
+		}
+
+		None
+	}
+
+	/// Number of disjoint runs of free frames, and the length of the
+	/// longest one, in bytes. Used to report fragmentation.
+	fn free_run_stats(&self) -> (usize, usize) {
+		let mut runs = 0;
+		let mut longest = 0;
+		let mut current = 0;
+
+		for frame in 0..self.frame_count {
+			if self.is_clear(frame) {
+				if current == 0 {
+					runs += 1;
+				}
+				current += 1;
+				longest = longest.max(current);
+			} else {
+				current = 0;
+			}
+		}
+
+		(runs, longest * BasePageSize::SIZE)
+	}
+}
+
+static BITMAP: SpinlockIrqSave<Bitmap> = SpinlockIrqSave::new(Bitmap::new());
+static TOTAL_MEMORY: AtomicUsize = AtomicUsize::new(0);
+static USED_MEMORY: AtomicUsize = AtomicUsize::new(0);
+
+fn detect_from_uhyve() -> Result<(), ()> {
+	if !is_uhyve() {
+		return Err(());
+	}
+
+	let limit = get_limit();
+	if limit == 0 {
+		return Err(());
+	}
+
+	let base = mm::kernel_end_address().as_usize();
+	TOTAL_MEMORY.store(limit - base, Ordering::SeqCst);
+	BITMAP.lock().init(base, limit - base);
+
+	Ok(())
+}
+
+fn detect_from_qemu() -> Result<(), ()> {
+	let limit = get_limit();
+	if limit == 0 {
+		return Err(());
+	}
+
+	// The bitmap backend tracks a single contiguous range, so the boot info
+	// page and the kernel image itself are reserved up front instead of
+	// being carved out as separate free regions.
+	let base = get_ram_address().as_usize();
+	TOTAL_MEMORY.store(limit - base, Ordering::SeqCst);
+
+	let mut bitmap = BITMAP.lock();
+	bitmap.init(base, limit - base);
+
+	let boot_info = align_down!(get_boot_info_address().as_usize(), BasePageSize::SIZE);
+	bitmap.set(bitmap.frame_of(boot_info));
+	let mut reserved = BasePageSize::SIZE;
+
+	let kernel_start = mm::kernel_start_address().as_usize() - crate::KERNEL_STACK_SIZE;
+	let kernel_end = mm::kernel_end_address().as_usize();
+	for frame in bitmap.frame_of(kernel_start)..bitmap.frame_of(kernel_end) {
+		bitmap.set(frame);
+	}
+	reserved += kernel_end - kernel_start;
+
+	// The boot info page and the kernel image were just marked as permanently
+	// set above; account for them as used so `free_memory_size()` agrees
+	// with the freelist backend, which excludes them from its free ranges
+	// up front instead.
+	USED_MEMORY.fetch_add(reserved, Ordering::SeqCst);
+
+	Ok(())
+}
+
+pub fn init() {
+	detect_from_uhyve()
+		.or_else(|_e| detect_from_qemu())
+		.expect("Unable to determine physical address space!");
+}
+
+pub fn total_memory_size() -> usize {
+	TOTAL_MEMORY.load(Ordering::SeqCst)
+}
+
+/// Physical memory currently handed out by `allocate`/`allocate_aligned`
+/// or carved out by `reserve`, plus, after QEMU detection, the boot info
+/// page and the kernel/stack range.
+pub fn used_memory_size() -> usize {
+	USED_MEMORY.load(Ordering::SeqCst)
+}
+
+/// Physical memory currently available to `allocate`/`allocate_aligned`.
+pub fn free_memory_size() -> usize {
+	total_memory_size() - used_memory_size()
+}
+
+pub fn init_page_tables() {}
+
+/// Reserves a specific physical range, e.g. a framebuffer, ACPI/DT tables or a
+/// device MMIO window handed to us by the bootloader or firmware, so that it
+/// is excluded from the pool of frames handed out by `allocate`/`allocate_aligned`.
+///
+/// Returns an error, without changing any bit, if any frame in the range is
+/// already reserved.
+pub fn reserve(address: PhysAddr, size: usize) -> Result<(), AllocError> {
+	assert_eq!(
+		address.as_usize() % BasePageSize::SIZE,
+		0,
+		"Address {:#X} is not a multiple of {:#X}",
+		address,
+		BasePageSize::SIZE
+	);
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let mut bitmap = BITMAP.lock();
+	let frame_count = size / BasePageSize::SIZE;
+	let first_frame = bitmap
+		.frame_range(address.as_usize(), frame_count)
+		.ok_or(AllocError)?;
+
+	if !(first_frame..first_frame + frame_count).all(|frame| bitmap.is_clear(frame)) {
+		return Err(AllocError);
+	}
+
+	for frame in first_frame..first_frame + frame_count {
+		bitmap.set(frame);
+	}
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(())
+}
+
+pub fn allocate(size: usize) -> Result<PhysAddr, AllocError> {
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let mut bitmap = BITMAP.lock();
+	let frame = bitmap
+		.find_and_set(size / BasePageSize::SIZE, 1)
+		.ok_or(AllocError)?;
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(PhysAddr(
+		(bitmap.base + frame * BasePageSize::SIZE)
+			.try_into()
+			.unwrap(),
+	))
+}
+
+pub fn allocate_aligned(size: usize, alignment: usize) -> Result<PhysAddr, AllocError> {
+	assert!(size > 0);
+	assert!(alignment > 0);
+	assert_eq!(
+		size % alignment,
+		0,
+		"Size {:#X} is not a multiple of the given alignment {:#X}",
+		size,
+		alignment
+	);
+	assert_eq!(
+		alignment % BasePageSize::SIZE,
+		0,
+		"Alignment {:#X} is not a multiple of {:#X}",
+		alignment,
+		BasePageSize::SIZE
+	);
+
+	let mut bitmap = BITMAP.lock();
+	let frame = bitmap
+		.find_and_set(size / BasePageSize::SIZE, alignment / BasePageSize::SIZE)
+		.ok_or(AllocError)?;
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(PhysAddr(
+		(bitmap.base + frame * BasePageSize::SIZE)
+			.try_into()
+			.unwrap(),
+	))
+}
+
+/// Frees a range previously returned by `allocate`/`allocate_aligned`, or
+/// carved out by `reserve`.
+///
+/// Matches `freelist_backend::deallocate`'s signature so that toggling
+/// between `frame_freelist` and `frame_bitmap` does not change how callers
+/// need to handle the result: returns an error, without clearing any bit,
+/// if any part of the range falls outside the tracked RAM span.
+pub fn deallocate(physical_address: PhysAddr, size: usize) -> Result<(), AllocError> {
+	assert!(
+		physical_address >= PhysAddr(mm::kernel_end_address().as_u64()),
+		"Physical address {:#X} is not >= KERNEL_END_ADDRESS",
+		physical_address
+	);
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let mut bitmap = BITMAP.lock();
+	let frame_count = size / BasePageSize::SIZE;
+	let first_frame = bitmap
+		.frame_range(physical_address.as_usize(), frame_count)
+		.ok_or(AllocError)?;
+
+	for frame in first_frame..first_frame + frame_count {
+		bitmap.clear(frame);
+	}
+	USED_MEMORY.fetch_sub(size, Ordering::SeqCst);
+
+	Ok(())
+}
+
+pub fn print_information() {
+	let (free_runs, largest_free_run) = BITMAP.lock().free_run_stats();
+
+	infoheader!(" PHYSICAL MEMORY BITMAP ");
+	info!("total memory: {:#X}", total_memory_size());
+	info!("used memory: {:#X}", used_memory_size());
+	info!("free memory: {:#X}", free_memory_size());
+	info!("free runs: {}", free_runs);
+	info!("largest free range: {:#X}", largest_free_run);
+	infofooter!();
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::boxed::Box;
+
+	use super::*;
+
+	/// `Bitmap` is large enough that constructing it on the stack risks an
+	/// overflow; box it instead, as a real allocator backend would be a
+	/// static anyway.
+	fn new_bitmap() -> Box<Bitmap> {
+		Box::new(Bitmap::new())
+	}
+
+	#[test]
+	fn find_and_set_returns_frames_in_order() {
+		let mut bitmap = new_bitmap();
+		bitmap.init(0, 4 * BasePageSize::SIZE);
+
+		let first = bitmap.find_and_set(1, 1).unwrap();
+		let second = bitmap.find_and_set(1, 1).unwrap();
+
+		assert_eq!(first, 0);
+		assert_eq!(second, 1);
+		assert!(!bitmap.is_clear(0));
+		assert!(!bitmap.is_clear(1));
+		assert!(bitmap.is_clear(2));
+	}
+
+	#[test]
+	fn clear_frees_frames_for_reuse() {
+		let mut bitmap = new_bitmap();
+		bitmap.init(0, 2 * BasePageSize::SIZE);
+
+		let frame = bitmap.find_and_set(2, 1).unwrap();
+		bitmap.clear(frame);
+		bitmap.clear(frame + 1);
+
+		assert_eq!(
+			bitmap.free_run_stats(),
+			(1, 2 * BasePageSize::SIZE)
+		);
+	}
+
+	#[test]
+	fn free_run_stats_reports_the_longest_run() {
+		let mut bitmap = new_bitmap();
+		bitmap.init(0, 4 * BasePageSize::SIZE);
+
+		// Splits the 4-frame free run into a 1-frame and a 2-frame run.
+		bitmap.set(1);
+
+		assert_eq!(
+			bitmap.free_run_stats(),
+			(2, 2 * BasePageSize::SIZE)
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "exceeds")]
+	fn init_rejects_more_memory_than_the_bitmap_can_track() {
+		new_bitmap().init(0, MAX_PHYSICAL_MEMORY + BasePageSize::SIZE);
+	}
+
+	#[test]
+	fn frame_range_rejects_addresses_outside_the_tracked_span() {
+		let mut bitmap = new_bitmap();
+		bitmap.init(0x4000_0000, 4 * BasePageSize::SIZE);
+
+		// Below the tracked span: would underflow `address - self.base`.
+		assert_eq!(bitmap.frame_range(0x0900_0000, 1), None);
+		// Right at the end of the tracked span: one frame too many.
+		assert_eq!(bitmap.frame_range(0x4000_0000, 5), None);
+		// Fully inside the tracked span.
+		assert_eq!(bitmap.frame_range(0x4000_0000, 4), Some(0));
+	}
+
+	#[test]
+	fn reserve_rejects_an_out_of_range_address_instead_of_panicking() {
+		BITMAP.lock().init(0x4000_0000, 4 * BasePageSize::SIZE);
+
+		let result = reserve(PhysAddr(0x0900_0000), BasePageSize::SIZE);
+
+		assert!(result.is_err());
+	}
+}
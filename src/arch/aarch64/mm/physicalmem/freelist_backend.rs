@@ -0,0 +1,228 @@
+use core::alloc::AllocError;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::arch::aarch64::kernel::{get_boot_info_address, get_limit, get_ram_address};
+use crate::arch::aarch64::mm::paging::{BasePageSize, PageSize};
+use crate::arch::aarch64::mm::{PhysAddr, VirtAddr};
+use crate::environment::is_uhyve;
+use crate::mm;
+use crate::mm::freelist::{FreeList, PageLayout, PageRange};
+use crate::synch::spinlock::SpinlockIrqSave;
+
+/// Number of free ranges the physical free list can hold at once. Detection
+/// populates at most three ranges (before/after the boot info page, plus the
+/// tail past the kernel), and reservations/fragmentation from here on split
+/// entries rather than allocate new node storage, so this is sized generously.
+const FREE_LIST_ENTRIES: usize = 16;
+
+static PHYSICAL_FREE_LIST: SpinlockIrqSave<FreeList<FREE_LIST_ENTRIES>> =
+	SpinlockIrqSave::new(FreeList::new());
+static TOTAL_MEMORY: AtomicUsize = AtomicUsize::new(0);
+static USED_MEMORY: AtomicUsize = AtomicUsize::new(0);
+
+fn detect_from_uhyve() -> Result<(), ()> {
+	if !is_uhyve() {
+		return Err(());
+	}
+
+	let limit = get_limit();
+	if limit == 0 {
+		return Err(());
+	}
+
+	let range = PageRange::new(mm::kernel_end_address().as_usize(), limit);
+	TOTAL_MEMORY.store(range.len(), Ordering::SeqCst);
+	PHYSICAL_FREE_LIST
+		.lock()
+		.push_back(range)
+		.expect("physical free list has no slot left for the uhyve range");
+
+	Ok(())
+}
+
+fn detect_from_qemu() -> Result<(), ()> {
+	let limit = get_limit();
+	if limit == 0 {
+		return Err(());
+	}
+
+	let ram_address = get_ram_address().as_usize();
+	let boot_info = align_down!(get_boot_info_address().as_usize(), BasePageSize::SIZE);
+	let kernel_start = mm::kernel_start_address().as_usize() - crate::KERNEL_STACK_SIZE;
+	let kernel_end = mm::kernel_end_address().as_usize();
+
+	let mut free_list = PHYSICAL_FREE_LIST.lock();
+
+	free_list
+		.push_back(PageRange::new(ram_address, boot_info))
+		.expect("physical free list has no slot left for the pre-boot-info range");
+	free_list
+		.push_back(PageRange::new(boot_info + BasePageSize::SIZE, kernel_start))
+		.expect("physical free list has no slot left for the pre-kernel range");
+	free_list
+		.push_back(PageRange::new(kernel_end, limit))
+		.expect("physical free list has no slot left for the post-kernel range");
+
+	// `TOTAL_MEMORY` is the whole detected RAM span, matching the bitmap
+	// backend; the boot info page and the kernel/stack range are excluded
+	// from the free list above but accounted for in `USED_MEMORY` instead,
+	// so `total_memory_size()`/`used_memory_size()` mean the same thing
+	// regardless of which backend is selected.
+	TOTAL_MEMORY.store(limit - ram_address, Ordering::SeqCst);
+	USED_MEMORY.fetch_add(
+		BasePageSize::SIZE + (kernel_end - kernel_start),
+		Ordering::SeqCst,
+	);
+
+	Ok(())
+}
+
+pub fn init() {
+	detect_from_uhyve()
+		.or_else(|_e| detect_from_qemu())
+		.expect("Unable to determine physical address space!");
+}
+
+pub fn total_memory_size() -> usize {
+	TOTAL_MEMORY.load(Ordering::SeqCst)
+}
+
+/// Physical memory currently handed out by `allocate`/`allocate_aligned`
+/// or carved out by `reserve`, plus, after QEMU detection, the boot info
+/// page and the kernel/stack range.
+pub fn used_memory_size() -> usize {
+	USED_MEMORY.load(Ordering::SeqCst)
+}
+
+/// Physical memory currently available to `allocate`/`allocate_aligned`.
+pub fn free_memory_size() -> usize {
+	total_memory_size() - used_memory_size()
+}
+
+pub fn init_page_tables() {}
+
+/// Reserves a specific physical range, e.g. a framebuffer, ACPI/DT tables or a
+/// device MMIO window handed to us by the bootloader or firmware, so that it
+/// is excluded from the pool of frames handed out by `allocate`/`allocate_aligned`.
+///
+/// The range must lie entirely within a single free entry; otherwise the free
+/// list is left untouched and an error is returned.
+pub fn reserve(address: PhysAddr, size: usize) -> Result<(), AllocError> {
+	assert_eq!(
+		address.as_usize() % BasePageSize::SIZE,
+		0,
+		"Address {:#X} is not a multiple of {:#X}",
+		address,
+		BasePageSize::SIZE
+	);
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let start = address.as_usize();
+	PHYSICAL_FREE_LIST
+		.lock()
+		.reserve(PageRange::new(start, start + size))?;
+
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(())
+}
+
+pub fn allocate(size: usize) -> Result<PhysAddr, AllocError> {
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let range = PHYSICAL_FREE_LIST
+		.lock()
+		.allocate(PageLayout::new(size, BasePageSize::SIZE))?;
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(PhysAddr(range.start.try_into().unwrap()))
+}
+
+pub fn allocate_aligned(size: usize, alignment: usize) -> Result<PhysAddr, AllocError> {
+	assert!(size > 0);
+	assert!(alignment > 0);
+	assert_eq!(
+		size % alignment,
+		0,
+		"Size {:#X} is not a multiple of the given alignment {:#X}",
+		size,
+		alignment
+	);
+	assert_eq!(
+		alignment % BasePageSize::SIZE,
+		0,
+		"Alignment {:#X} is not a multiple of {:#X}",
+		alignment,
+		BasePageSize::SIZE
+	);
+
+	let range = PHYSICAL_FREE_LIST
+		.lock()
+		.allocate(PageLayout::new(size, alignment))?;
+	USED_MEMORY.fetch_add(size, Ordering::SeqCst);
+
+	Ok(PhysAddr(range.start.try_into().unwrap()))
+}
+
+/// Frees a range previously returned by `allocate`/`allocate_aligned`.
+///
+/// Unlike the former linked-list implementation, this does not depend on a
+/// separately-maintained node pool: entries live in a fixed-capacity inline
+/// array and freeing usually just merges with or replaces an existing
+/// entry. It can still fail if `physical_address` is not adjacent to a free
+/// entry and the array already holds its full `N` disjoint free ranges;
+/// that error is propagated instead of panicking.
+pub fn deallocate(physical_address: PhysAddr, size: usize) -> Result<(), AllocError> {
+	assert!(
+		physical_address >= PhysAddr(mm::kernel_end_address().as_u64()),
+		"Physical address {:#X} is not >= KERNEL_END_ADDRESS",
+		physical_address
+	);
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let start = physical_address.as_usize();
+	PHYSICAL_FREE_LIST
+		.lock()
+		.deallocate(PageRange::new(start, start + size))?;
+	USED_MEMORY.fetch_sub(size, Ordering::SeqCst);
+
+	Ok(())
+}
+
+pub fn print_information() {
+	let free_list = PHYSICAL_FREE_LIST.lock();
+
+	infoheader!(" PHYSICAL MEMORY FREE LIST ");
+	info!("total memory: {:#X}", total_memory_size());
+	info!("used memory: {:#X}", used_memory_size());
+	info!("free memory: {:#X}", free_memory_size());
+	info!("free list entries: {}", free_list.len());
+	info!(
+		"largest free range: {:#X}",
+		free_list.largest_free_range()
+	);
+	infofooter!();
+
+	free_list.print_information(" PHYSICAL MEMORY FREE LIST ENTRIES ");
+}
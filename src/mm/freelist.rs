@@ -0,0 +1,326 @@
+//! A bounded free list of page ranges backed by a fixed-capacity inline
+//! array, so that allocating and freeing from it never touches the heap or
+//! a separately-maintained node pool.
+
+use core::alloc::AllocError;
+
+/// A half-open range of pages `[start, end)`, given in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRange {
+	pub start: usize,
+	pub end: usize,
+}
+
+impl PageRange {
+	pub const fn new(start: usize, end: usize) -> Self {
+		assert!(start <= end);
+		Self { start, end }
+	}
+
+	pub const fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	pub const fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+}
+
+/// A request for a range of `size` bytes aligned to `align`.
+#[derive(Clone, Copy, Debug)]
+pub struct PageLayout {
+	pub size: usize,
+	pub align: usize,
+}
+
+impl PageLayout {
+	pub const fn new(size: usize, align: usize) -> Self {
+		Self { size, align }
+	}
+
+	/// A layout with no alignment requirement beyond a single byte.
+	pub const fn from_size(size: usize) -> Self {
+		Self::new(size, 1)
+	}
+}
+
+fn align_up(address: usize, align: usize) -> usize {
+	(address + align - 1) & !(align - 1)
+}
+
+/// A free list whose entries live in a fixed-capacity inline array of `N`
+/// slots. Allocation walks the array for an entry satisfying the requested
+/// [`PageLayout`] and splits it in place; deallocation merges the freed
+/// [`PageRange`] with adjacent entries. Neither path allocates.
+pub struct FreeList<const N: usize> {
+	entries: [Option<PageRange>; N],
+	len: usize,
+}
+
+impl<const N: usize> FreeList<N> {
+	pub const fn new() -> Self {
+		Self {
+			entries: [None; N],
+			len: 0,
+		}
+	}
+
+	/// Number of occupied entries.
+	pub const fn len(&self) -> usize {
+		self.len
+	}
+
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Inserts a range as a new entry. Empty ranges are ignored.
+	///
+	/// Returns an error if the list has no free slot left.
+	pub fn push_back(&mut self, range: PageRange) -> Result<(), AllocError> {
+		if range.is_empty() {
+			return Ok(());
+		}
+
+		let slot = self
+			.entries
+			.iter_mut()
+			.find(|entry| entry.is_none())
+			.ok_or(AllocError)?;
+		*slot = Some(range);
+		self.len += 1;
+
+		Ok(())
+	}
+
+	/// Finds an entry that can satisfy `layout` and splits it in place,
+	/// returning the carved-out range. Any head/tail fragments left over by
+	/// the split are re-inserted as new entries.
+	///
+	/// An entry is only split once there is provably room for every
+	/// resulting fragment, so a failure never leaves the list partially
+	/// mutated or drops a range on the floor.
+	pub fn allocate(&mut self, layout: PageLayout) -> Result<PageRange, AllocError> {
+		for i in 0..N {
+			let Some(entry) = self.entries[i] else {
+				continue;
+			};
+
+			let start = align_up(entry.start, layout.align);
+			let end = start + layout.size;
+			if end > entry.end {
+				continue;
+			}
+
+			let head = PageRange::new(entry.start, start);
+			let tail = PageRange::new(end, entry.end);
+			if !self.has_room_for_split(&head, &tail) {
+				continue;
+			}
+
+			self.entries[i] = None;
+			self.len -= 1;
+			if !head.is_empty() {
+				self.push_back(head).expect("room for the split was just checked");
+			}
+			if !tail.is_empty() {
+				self.push_back(tail).expect("room for the split was just checked");
+			}
+
+			return Ok(PageRange::new(start, end));
+		}
+
+		Err(AllocError)
+	}
+
+	/// Carves `range` out of whichever entry fully contains it, re-inserting
+	/// the head/tail fragments that remain. Returns an error, leaving the
+	/// list untouched, if no single entry contains `range` or if the split
+	/// would need more free slots than the list has left.
+	pub fn reserve(&mut self, range: PageRange) -> Result<(), AllocError> {
+		for i in 0..N {
+			let Some(entry) = self.entries[i] else {
+				continue;
+			};
+
+			if entry.start > range.start || range.end > entry.end {
+				continue;
+			}
+
+			let head = PageRange::new(entry.start, range.start);
+			let tail = PageRange::new(range.end, entry.end);
+			if !self.has_room_for_split(&head, &tail) {
+				return Err(AllocError);
+			}
+
+			self.entries[i] = None;
+			self.len -= 1;
+			if !head.is_empty() {
+				self.push_back(head).expect("room for the split was just checked");
+			}
+			if !tail.is_empty() {
+				self.push_back(tail).expect("room for the split was just checked");
+			}
+
+			return Ok(());
+		}
+
+		Err(AllocError)
+	}
+
+	/// Whether, after freeing the entry currently being split, there is
+	/// still a slot for every non-empty fragment of `head`/`tail`.
+	fn has_room_for_split(&self, head: &PageRange, tail: &PageRange) -> bool {
+		let fragments = usize::from(!head.is_empty()) + usize::from(!tail.is_empty());
+		let available_after_freeing_current_entry = N - self.len + 1;
+		fragments <= available_after_freeing_current_entry
+	}
+
+	/// Frees `range`, merging it with any adjacent entry so that the list
+	/// does not fragment needlessly.
+	///
+	/// Returns an error, leaving `range` untracked by this list, if `range`
+	/// is not adjacent to an existing entry and the list has no free slot
+	/// left to hold it as a new one. This can only happen once there are
+	/// already `N` disjoint free ranges, i.e. under allocator-metadata
+	/// exhaustion rather than as a routine failure.
+	pub fn deallocate(&mut self, range: PageRange) -> Result<(), AllocError> {
+		if range.is_empty() {
+			return Ok(());
+		}
+
+		let mut merged = range;
+		let left = self
+			.entries
+			.iter()
+			.position(|entry| matches!(entry, Some(e) if e.end == merged.start));
+		let right = self
+			.entries
+			.iter()
+			.position(|entry| matches!(entry, Some(e) if e.start == merged.end));
+
+		if let Some(i) = left {
+			merged.start = self.entries[i].unwrap().start;
+		}
+		if let Some(i) = right {
+			merged.end = self.entries[i].unwrap().end;
+		}
+
+		match (left, right) {
+			(Some(i), Some(j)) if i != j => {
+				self.entries[j] = None;
+				self.entries[i] = Some(merged);
+				self.len -= 1;
+			}
+			(Some(i), _) | (_, Some(i)) => {
+				self.entries[i] = Some(merged);
+			}
+			(None, None) => {
+				self.push_back(merged)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Size, in bytes, of the largest contiguous free range.
+	pub fn largest_free_range(&self) -> usize {
+		self.entries
+			.iter()
+			.flatten()
+			.map(PageRange::len)
+			.max()
+			.unwrap_or(0)
+	}
+
+	pub fn print_information(&self, header: &str) {
+		infoheader!(header);
+		for entry in self.entries.iter().flatten() {
+			info!("{:#016X} - {:#016X}", entry.start, entry.end);
+		}
+		infofooter!();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PAGE: usize = 0x1000;
+
+	#[test]
+	fn allocate_then_merge_on_deallocate() {
+		let mut list: FreeList<4> = FreeList::new();
+		list.push_back(PageRange::new(0, 4 * PAGE)).unwrap();
+
+		let allocated = list.allocate(PageLayout::new(PAGE, PAGE)).unwrap();
+		assert_eq!(allocated, PageRange::new(0, PAGE));
+		assert_eq!(list.len(), 1);
+		assert_eq!(list.largest_free_range(), 3 * PAGE);
+
+		list.deallocate(allocated).unwrap();
+		assert_eq!(list.len(), 1);
+		assert_eq!(list.largest_free_range(), 4 * PAGE);
+	}
+
+	#[test]
+	fn reserve_splits_an_entry_on_both_sides() {
+		let mut list: FreeList<4> = FreeList::new();
+		list.push_back(PageRange::new(0, 4 * PAGE)).unwrap();
+
+		list.reserve(PageRange::new(PAGE, 2 * PAGE)).unwrap();
+
+		assert_eq!(list.len(), 2);
+		assert_eq!(list.largest_free_range(), 2 * PAGE);
+	}
+
+	#[test]
+	fn reserve_without_a_containing_entry_is_rejected() {
+		let mut list: FreeList<4> = FreeList::new();
+		list.push_back(PageRange::new(0, PAGE)).unwrap();
+
+		assert!(list.reserve(PageRange::new(PAGE, 2 * PAGE)).is_err());
+		assert_eq!(list.len(), 1);
+		assert_eq!(list.largest_free_range(), PAGE);
+	}
+
+	#[test]
+	fn deallocate_merges_with_both_neighbours() {
+		let mut list: FreeList<4> = FreeList::new();
+		list.push_back(PageRange::new(0, PAGE)).unwrap();
+		list.push_back(PageRange::new(2 * PAGE, 3 * PAGE)).unwrap();
+
+		list.deallocate(PageRange::new(PAGE, 2 * PAGE)).unwrap();
+
+		assert_eq!(list.len(), 1);
+		assert_eq!(list.largest_free_range(), 3 * PAGE);
+	}
+
+	#[test]
+	fn deallocate_without_a_free_slot_or_neighbour_reports_an_error() {
+		let mut list: FreeList<2> = FreeList::new();
+		list.push_back(PageRange::new(0, PAGE)).unwrap();
+		list.push_back(PageRange::new(2 * PAGE, 3 * PAGE)).unwrap();
+
+		let err = list.deallocate(PageRange::new(4 * PAGE, 5 * PAGE));
+
+		assert!(err.is_err());
+		assert_eq!(list.len(), 2);
+	}
+
+	#[test]
+	fn allocate_skips_an_entry_too_fragmented_to_split() {
+		// No free slot is left, so splitting the first entry (which would
+		// leave a head *and* a tail fragment) must be skipped in favour of
+		// the second, exactly-sized entry that needs no split at all.
+		let mut list: FreeList<2> = FreeList::new();
+		list.push_back(PageRange::new(PAGE, 5 * PAGE)).unwrap();
+		list.push_back(PageRange::new(8 * PAGE, 9 * PAGE)).unwrap();
+
+		let allocated = list
+			.allocate(PageLayout::new(PAGE, 2 * PAGE))
+			.unwrap();
+
+		assert_eq!(allocated, PageRange::new(8 * PAGE, 9 * PAGE));
+	}
+}